@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::path::PathBuf;
+
+use clap::{Arg, ArgMatches, Command};
+
+/// Arguments is the parsed and validated command line input.
+#[derive(Debug)]
+pub struct Arguments {
+    pub config: Option<PathBuf>,
+    pub mode: Mode,
+}
+
+/// Mode selects which part of Bear's pipeline to run.
+#[derive(Debug)]
+pub enum Mode {
+    /// Only capture the build commands into an event file.
+    Intercept {
+        input: BuildCommand,
+        output: BuildEvents,
+    },
+    /// Deduct the semantic meaning of the executed commands and write a
+    /// compilation database.
+    Semantic {
+        input: SemanticInput,
+        output: BuildSemantic,
+    },
+    /// Run the build command and produce the compilation database in one go.
+    All {
+        input: BuildCommand,
+        output: BuildSemantic,
+    },
+}
+
+/// The build command to execute and intercept.
+#[derive(Debug)]
+pub struct BuildCommand {
+    pub arguments: Vec<String>,
+}
+
+/// The file that intercepted events are written to (or read from).
+#[derive(Debug, Clone)]
+pub struct BuildEvents {
+    pub file_name: PathBuf,
+}
+
+/// Where the semantic pipeline reads `intercept::Execution`s from.
+#[derive(Debug)]
+pub enum SemanticInput {
+    /// Replay executions previously recorded by an intercept run.
+    Events(BuildEvents),
+    /// Derive executions from a `cargo build --message-format=json` stream,
+    /// without any intercepting at all.
+    CargoBuildLog(CargoBuildLog),
+}
+
+/// A `cargo build --message-format=json` stream to derive executions from.
+///
+/// Cargo's verbose output reports each rustc invocation's command line but
+/// not the directory it ran in, so `working_dir` has to come from the
+/// caller: the directory `cargo build` itself was run from.
+#[derive(Debug)]
+pub struct CargoBuildLog {
+    pub file_name: PathBuf,
+    pub working_dir: PathBuf,
+}
+
+/// The file that the compilation database is written to.
+#[derive(Debug)]
+pub struct BuildSemantic {
+    pub file_name: PathBuf,
+    pub append: bool,
+}
+
+/// Build the command line interface definition.
+pub fn cli() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Path to the configuration file"),
+        )
+}
+
+impl TryFrom<ArgMatches> for Arguments {
+    type Error = anyhow::Error;
+
+    fn try_from(matches: ArgMatches) -> Result<Self, Self::Error> {
+        let config = matches.get_one::<String>("config").map(PathBuf::from);
+        // TODO: parse the `intercept`/`semantic`/`all` subcommands and their
+        // own arguments once they are implemented.
+        Err(anyhow::anyhow!(
+            "no mode given on the command line (config: {:?})",
+            config
+        ))
+    }
+}
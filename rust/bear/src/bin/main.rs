@@ -1,7 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use std::process::ExitCode;
 
-use bear::input::EventFileReader;
+use bear::input::EventSource;
+use bear::lockfile::Lock;
 use bear::output::OutputWriter;
 use bear::{args, config, semantic};
 use intercept::Execution;
@@ -41,11 +42,15 @@ enum Application {
         input: args::BuildCommand,
         output: args::BuildEvents,
         intercept_config: config::Intercept,
+        // Held for the lifetime of the application; dropping it releases
+        // the lock on the build events file.
+        _lock: Lock,
     },
     /// The semantic mode we are deduct the semantic meaning of the
     /// executed commands from the build process.
     Semantic {
-        event_source: EventFileReader,
+        event_source: EventSource,
+        compiler_filter: config::CompilerFilter,
         semantic_recognition: SemanticRecognition,
         semantic_transform: SemanticTransform,
         output_writer: OutputWriter,
@@ -56,6 +61,9 @@ enum Application {
         output: args::BuildSemantic,
         intercept_config: config::Intercept,
         output_config: config::Output,
+        // Held for the lifetime of the application; dropping it releases
+        // the lock on the compilation database output.
+        _lock: Lock,
     },
 }
 
@@ -69,20 +77,27 @@ impl Application {
         match args.mode {
             args::Mode::Intercept { input, output } => {
                 let intercept_config = config.intercept;
+                let lock = Lock::acquire(&output.file_name)?;
                 let result = Application::Intercept {
                     input,
                     output,
                     intercept_config,
+                    _lock: lock,
                 };
                 Ok(result)
             }
             args::Mode::Semantic { input, output } => {
-                let event_source = EventFileReader::try_from(input)?;
+                let event_source = EventSource::try_from(input)?;
+                let compiler_filter = match &config.output {
+                    config::Output::Clang { filter, .. } => filter.compilers.clone(),
+                    config::Output::Semantic { .. } => config::CompilerFilter::default(),
+                };
                 let semantic_recognition = SemanticRecognition::try_from(&config)?;
                 let semantic_transform = SemanticTransform::from(&config.output);
                 let output_writer = OutputWriter::configure(&output, &config.output);
                 let result = Application::Semantic {
                     event_source,
+                    compiler_filter,
                     semantic_recognition,
                     semantic_transform,
                     output_writer,
@@ -92,11 +107,13 @@ impl Application {
             args::Mode::All { input, output } => {
                 let intercept_config = config.intercept;
                 let output_config = config.output;
+                let lock = Lock::acquire(&output.file_name)?;
                 let result = Application::All {
                     input,
                     output,
                     intercept_config,
                     output_config,
+                    _lock: lock,
                 };
                 Ok(result)
             }
@@ -110,19 +127,21 @@ impl Application {
                 input,
                 output,
                 intercept_config,
+                _lock,
             } => {
                 // TODO: Implement the intercept mode.
                 ExitCode::FAILURE
             }
             Application::Semantic {
                 event_source,
+                compiler_filter,
                 semantic_recognition,
                 semantic_transform,
                 output_writer,
             } => {
                 // Set up the pipeline of compilation database entries.
                 let entries = event_source
-                    .generate()
+                    .generate(&compiler_filter)
                     .flat_map(|execution| semantic_recognition.apply(execution))
                     .map(|semantic| semantic_transform.apply(semantic));
                 // Consume the entries and write them to the output file.
@@ -137,6 +156,7 @@ impl Application {
                 output,
                 intercept_config,
                 output_config,
+                _lock,
             } => {
                 // TODO: Implement the all mode.
                 ExitCode::FAILURE
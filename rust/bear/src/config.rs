@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::path::{Path, PathBuf};
+
+use intercept::Codec;
+use serde::{Deserialize, Serialize};
+
+/// Main is the root of the configuration file.
+///
+/// It is loaded from the path given on the command line, falling back to
+/// the built-in defaults when no configuration file is present.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Main {
+    #[serde(default)]
+    pub intercept: Intercept,
+    #[serde(default)]
+    pub output: Output,
+}
+
+impl Main {
+    /// Load the configuration from `path`, or fall back to the defaults
+    /// when no path is given.
+    pub fn load(path: &Option<PathBuf>) -> Result<Self, anyhow::Error> {
+        match path {
+            Some(path) => Self::load_from(path),
+            None => Ok(Main::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Self, anyhow::Error> {
+        let content = std::fs::read_to_string(path)?;
+        let config = serde_json::from_str(&content)?;
+        Ok(config)
+    }
+}
+
+impl Default for Main {
+    fn default() -> Self {
+        Main {
+            intercept: Intercept::default(),
+            output: Output::default(),
+        }
+    }
+}
+
+/// Intercept describes how build commands are captured.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "method")]
+pub enum Intercept {
+    /// Capture commands by replacing the compiler with a wrapper script.
+    Wrapper {
+        #[serde(default)]
+        executables: Vec<PathBuf>,
+        /// Wire format used between the wrapper's reporter and the
+        /// collector. Defaults to JSON for interoperability; MsgPack trades
+        /// that off for lower-overhead decoding on large builds.
+        #[serde(default)]
+        codec: Codec,
+    },
+    /// Capture commands via the dynamic linker's `LD_PRELOAD` mechanism.
+    Preload {
+        #[serde(default)]
+        codec: Codec,
+    },
+}
+
+impl Default for Intercept {
+    fn default() -> Self {
+        Intercept::Wrapper {
+            executables: vec![],
+            codec: Codec::default(),
+        }
+    }
+}
+
+/// Output describes how the recognized compiler calls are turned into the
+/// final artifact.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "format")]
+pub enum Output {
+    /// Emit a clang compilation database (`compile_commands.json`).
+    Clang {
+        #[serde(default)]
+        filter: Filter,
+        #[serde(default)]
+        transform: Transform,
+    },
+    /// Emit the recognized semantics without any clang-specific shaping.
+    Semantic {},
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output::Clang {
+            filter: Filter::default(),
+            transform: Transform::default(),
+        }
+    }
+}
+
+/// Filter narrows down which recognized compiler calls are kept.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Filter {
+    #[serde(default)]
+    pub compilers: CompilerFilter,
+}
+
+/// CompilerFilter excludes compiler calls by executable path or by argument.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CompilerFilter {
+    #[serde(default)]
+    pub with_paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub with_arguments: Vec<String>,
+    /// Drop commands whose `Finished` event reported a non-zero exit code.
+    /// Commands with no matching `Finished` event (still running, or the
+    /// build ended first) are kept, same as before outcomes existed.
+    #[serde(default)]
+    pub only_successful_commands: bool,
+}
+
+/// Transform rewrites the compiler flags of every kept compilation entry.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Transform {
+    #[serde(default)]
+    pub arguments_to_add: Vec<String>,
+    #[serde(default)]
+    pub arguments_to_remove: Vec<String>,
+}
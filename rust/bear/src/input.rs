@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use intercept::{Envelope, Event, Execution, ProcessId, ReporterId};
+use serde::Deserialize;
+
+use crate::{args, config};
+
+/// EventSource produces `intercept::Execution`s for the semantic pipeline,
+/// regardless of where they actually came from.
+pub enum EventSource {
+    /// Executions recorded by an intercept run, replayed from its event file.
+    Events(EventFileReader),
+    /// Executions derived from a `cargo build --message-format=json` stream.
+    CargoBuildLog(CargoMessageReader),
+}
+
+impl TryFrom<args::SemanticInput> for EventSource {
+    type Error = anyhow::Error;
+
+    fn try_from(input: args::SemanticInput) -> Result<Self, Self::Error> {
+        match input {
+            args::SemanticInput::Events(events) => {
+                Ok(EventSource::Events(EventFileReader::try_from(events)?))
+            }
+            args::SemanticInput::CargoBuildLog(log) => Ok(EventSource::CargoBuildLog(
+                CargoMessageReader::try_from(log)?,
+            )),
+        }
+    }
+}
+
+impl EventSource {
+    /// Generate the executions read from this source.
+    ///
+    /// `filter` only affects the `Events` source: a cargo build log has no
+    /// `Finished` events to filter on, so it is ignored there.
+    pub fn generate(self, filter: &config::CompilerFilter) -> Box<dyn Iterator<Item = Execution>> {
+        match self {
+            EventSource::Events(reader) => Box::new(reader.generate(filter)),
+            EventSource::CargoBuildLog(reader) => Box::new(reader.generate()),
+        }
+    }
+}
+
+/// EventFileReader replays `intercept::Execution`s previously recorded by
+/// the intercept collector into the build events file.
+pub struct EventFileReader {
+    reader: BufReader<File>,
+}
+
+impl TryFrom<args::BuildEvents> for EventFileReader {
+    type Error = anyhow::Error;
+
+    fn try_from(input: args::BuildEvents) -> Result<Self, Self::Error> {
+        let file = File::open(&input.file_name)?;
+        Ok(EventFileReader {
+            reader: BufReader::new(file),
+        })
+    }
+}
+
+impl EventFileReader {
+    /// Read every envelope from the event file and yield the execution
+    /// carried by each `Started` event, stopping at the first read failure
+    /// (which, at the end of a well-formed file, is simply EOF).
+    ///
+    /// With `filter.only_successful_commands` unset, a `Started` execution
+    /// is yielded right away: a process that never got to report its
+    /// outcome (the build ended first, or it is still running) is still
+    /// recognized from its `Started` event alone, same as before outcomes
+    /// existed. With it set, a `Started` execution is instead held back
+    /// until a matching `Finished` event arrives: a zero exit code yields
+    /// it, a non-zero one drops it, and one left pending at EOF (no
+    /// `Finished` ever arrived) is flushed and kept, same as when the
+    /// filter is unset.
+    pub fn generate(
+        mut self,
+        filter: &config::CompilerFilter,
+    ) -> impl Iterator<Item = Execution> {
+        let only_successful_commands = filter.only_successful_commands;
+        let mut pending: HashMap<(ReporterId, ProcessId), Execution> = HashMap::new();
+        let mut leftovers: Option<std::vec::IntoIter<Execution>> = None;
+
+        std::iter::from_fn(move || loop {
+            if let Some(leftovers) = leftovers.as_mut() {
+                return leftovers.next();
+            }
+
+            let envelope = match Envelope::read_from(&mut self.reader) {
+                Ok(envelope) => envelope,
+                Err(_) => {
+                    let flushed: Vec<Execution> =
+                        pending.drain().map(|(_, execution)| execution).collect();
+                    leftovers = Some(flushed.into_iter());
+                    continue;
+                }
+            };
+            let key = (envelope.rid.clone(), envelope.event.pid().clone());
+
+            match envelope.event {
+                Event::Started { execution, .. } => {
+                    if only_successful_commands {
+                        pending.insert(key, execution);
+                    } else {
+                        return Some(execution);
+                    }
+                }
+                Event::Finished { exit_code, .. } => {
+                    if let Some(execution) = pending.remove(&key) {
+                        if exit_code == 0 {
+                            return Some(execution);
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// A single JSON line of a `cargo build --message-format=json` stream.
+///
+/// None of these carry the rustc invocation: `compiler-artifact` names the
+/// built output, not the command that produced it, and the others carry no
+/// command at all. They are matched here to tell well-formed cargo JSON
+/// apart from malformed JSON, which is worth a warning, and to notice
+/// `compiler-artifact`/`build-script-executed` messages in a log that has no
+/// `Running` lines.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CargoMessage {
+    CompilerArtifact,
+    BuildScriptExecuted,
+    CompilerMessage,
+    BuildFinished,
+    #[serde(other)]
+    Other,
+}
+
+/// CargoMessageReader synthesizes `intercept::Execution`s straight from a
+/// `cargo build --message-format=json` stream, so that a compilation
+/// database can be produced without intercepting anything.
+///
+/// The JSON messages themselves don't carry rustc's command line, only its
+/// build artifacts, so the actual invocations are recovered from cargo's
+/// human-readable verbose (`-v`) output interleaved in the same stream:
+/// lines of the form `` Running `rustc ...` ``. The log must therefore come
+/// from `cargo build -v --message-format=json`; `generate` logs an error if
+/// it sees build messages with no `Running` lines to back them, rather than
+/// silently producing an empty result.
+pub struct CargoMessageReader {
+    reader: BufReader<File>,
+    working_dir: PathBuf,
+}
+
+impl TryFrom<args::CargoBuildLog> for CargoMessageReader {
+    type Error = anyhow::Error;
+
+    fn try_from(log: args::CargoBuildLog) -> Result<Self, Self::Error> {
+        let file = File::open(log.file_name)?;
+        Ok(CargoMessageReader {
+            reader: BufReader::new(file),
+            working_dir: log.working_dir,
+        })
+    }
+}
+
+impl CargoMessageReader {
+    /// Parse the stream and synthesize an execution for every `Running`
+    /// line, which is the only place the stream names an actual rustc
+    /// invocation.
+    ///
+    /// A plain `cargo build --message-format=json` run, without `-v`, emits
+    /// `compiler-artifact`/`build-script-executed` messages but no `Running`
+    /// lines at all, which would otherwise yield zero executions with no
+    /// diagnostic. To surface that rather than bury it, this tracks whether
+    /// such a build message was seen while no `Running` line at all was seen
+    /// by the end of the stream, and logs an actionable error if so.
+    pub fn generate(self) -> impl Iterator<Item = Execution> {
+        let working_dir = self.working_dir;
+        let mut lines = self.reader.lines();
+        let mut saw_build_message = false;
+        let mut saw_running_line = false;
+
+        std::iter::from_fn(move || loop {
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(error)) => {
+                    log::error!("failed to read cargo build log line: {:?}", error);
+                    return None;
+                }
+                None => {
+                    if saw_build_message && !saw_running_line {
+                        log::error!(
+                            "cargo build log has compiler-artifact/build-script-executed messages \
+                             but no verbose `Running` lines; rerun with \
+                             `cargo build -v --message-format=json` to capture invocations"
+                        );
+                    }
+                    return None;
+                }
+            };
+
+            if let Some((environment, executable, arguments)) = parse_running_line(&line) {
+                saw_running_line = true;
+                return Some(Execution {
+                    executable,
+                    arguments,
+                    working_dir: working_dir.clone(),
+                    environment,
+                });
+            }
+
+            if line.trim_start().starts_with('{') {
+                match serde_json::from_str::<CargoMessage>(&line) {
+                    Ok(CargoMessage::CompilerArtifact | CargoMessage::BuildScriptExecuted) => {
+                        saw_build_message = true;
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        log::warn!("failed to parse cargo build log line: {:?}", error);
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Recognize a cargo verbose (`-v`) `` Running `...` `` line and split the
+/// wrapped command into its leading `KEY=VALUE` environment assignments and
+/// its executable plus arguments.
+///
+/// Returns `None` for anything else: plain JSON messages, ordinary progress
+/// text (`Compiling foo v0.1.0 (...)`), and the like.
+fn parse_running_line(line: &str) -> Option<(HashMap<String, String>, PathBuf, Vec<String>)> {
+    let line = line.trim();
+    let rest = line.strip_prefix("Running `")?;
+    let command = rest.strip_suffix('`')?;
+
+    let mut tokens = shell_split(command).into_iter();
+    let mut environment = HashMap::new();
+    let mut executable = None;
+
+    for token in &mut tokens {
+        match token.split_once('=') {
+            Some((key, value)) if is_env_key(key) => {
+                environment.insert(key.to_string(), value.to_string());
+            }
+            _ => {
+                executable = Some(PathBuf::from(token));
+                break;
+            }
+        }
+    }
+
+    let executable = executable?;
+    let arguments = std::iter::once(executable.to_string_lossy().into_owned())
+        .chain(tokens)
+        .collect();
+
+    Some((environment, executable, arguments))
+}
+
+/// Whether `key` looks like an environment variable name (`CARGO_PKG_NAME`,
+/// not a path or flag), distinguishing a leading `KEY=VALUE` assignment from
+/// the executable itself, which may also contain a literal `=`.
+fn is_env_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Split a shell command line into words, respecting single and double
+/// quoting the way cargo's verbose output emits them.
+fn shell_split(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None => match c {
+                '\'' | '"' => {
+                    quote = Some(c);
+                    in_word = true;
+                }
+                c if c.is_whitespace() => {
+                    if in_word {
+                        words.push(std::mem::take(&mut current));
+                        in_word = false;
+                    }
+                }
+                c => {
+                    current.push(c);
+                    in_word = true;
+                }
+            },
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+
+    words
+}
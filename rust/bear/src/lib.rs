@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+pub mod args;
+pub mod config;
+pub mod input;
+pub mod lockfile;
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Lock is an advisory, PID-stamped lockfile that keeps two `bear`
+/// invocations from writing to the same intercept output at once.
+///
+/// It lives next to the path it protects, named after it with a
+/// `.bear-lock` suffix, and is removed when the lock is dropped, on normal
+/// exit or on `SIGINT`/`SIGTERM`.
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Acquire the lock for `output_path`.
+    ///
+    /// Fails fast, naming the owning PID, if a live process already holds
+    /// the lock. A lock left behind by a process that is no longer alive is
+    /// considered stale and reclaimed.
+    pub fn acquire(output_path: &Path) -> Result<Self, anyhow::Error> {
+        let path = Self::lock_path(output_path);
+
+        if let Some(pid) = Self::read_owner(&path)? {
+            if Self::is_alive(pid) {
+                return Err(anyhow::anyhow!(
+                    "{} is locked by another bear process (pid {})",
+                    output_path.display(),
+                    pid
+                ));
+            }
+            log::warn!(
+                "reclaiming stale lock left by pid {} at {}",
+                pid,
+                path.display()
+            );
+            fs::remove_file(&path)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        write!(file, "{}", std::process::id())?;
+
+        Self::release_on_signal(path.clone());
+
+        Ok(Lock { path })
+    }
+
+    fn lock_path(output_path: &Path) -> PathBuf {
+        let mut name = output_path.as_os_str().to_owned();
+        name.push(".bear-lock");
+        PathBuf::from(name)
+    }
+
+    fn read_owner(path: &Path) -> Result<Option<u32>, anyhow::Error> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(content.trim().parse::<u32>().ok()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    #[cfg(unix)]
+    fn is_alive(pid: u32) -> bool {
+        // Signal 0 checks whether the process exists without affecting it.
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    fn is_alive(_pid: u32) -> bool {
+        true
+    }
+
+    /// Best-effort removal of the lockfile if the process is interrupted
+    /// instead of exiting normally through `Drop`.
+    fn release_on_signal(path: PathBuf) {
+        let result = ctrlc::set_handler(move || {
+            let _ = fs::remove_file(&path);
+            std::process::exit(130);
+        });
+        if let Err(error) = result {
+            log::warn!("failed to install signal handler for lock cleanup: {:?}", error);
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            log::warn!(
+                "failed to remove lock file {}: {:?}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+}
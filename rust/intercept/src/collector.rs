@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crate::{Codec, Envelope, Event, Execution, Hello, ProcessId, ReporterId, Version};
+
+/// How many not-yet-delivered executions a lagging subscriber can fall
+/// behind by before the oldest ones are dropped for it.
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 1024;
+
+/// Outcome reconstructs a full process record once both its `Started` and
+/// `Finished` events have been seen.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub execution: Execution,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+}
+
+/// Ledger pairs `Started` and `Finished` events sharing the same `(rid,
+/// pid)` identity, so a collector can learn how a command ended rather than
+/// only that it ran.
+#[derive(Default)]
+struct Ledger {
+    pending: HashMap<(ReporterId, ProcessId), Execution>,
+}
+
+impl Ledger {
+    /// Record `envelope`'s event, returning the reconstructed `Outcome` once
+    /// a matching pair has been seen. A `Started` with no matching
+    /// `Finished` yet (the process is still running, or the build ended
+    /// first) is left pending rather than treated as an error.
+    fn record(&mut self, envelope: &Envelope) -> Option<Outcome> {
+        let key = (envelope.rid.clone(), envelope.event.pid().clone());
+
+        match &envelope.event {
+            Event::Started { execution, .. } => {
+                self.pending.insert(key, execution.clone());
+                None
+            }
+            Event::Finished {
+                exit_code,
+                duration_ms,
+                ..
+            } => match self.pending.remove(&key) {
+                Some(execution) => Some(Outcome {
+                    execution,
+                    exit_code: *exit_code,
+                    duration_ms: *duration_ms,
+                }),
+                None => {
+                    log::warn!("finished event with no matching started event: {:?}", key);
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// A bounded, drop-oldest mailbox for one subscriber.
+///
+/// Built on plain `std::sync` primitives rather than an async channel, to
+/// match the rest of this module, which is blocking std I/O throughout.
+struct Mailbox {
+    queue: Mutex<VecDeque<Execution>>,
+    available: Condvar,
+}
+
+impl Mailbox {
+    fn new() -> Self {
+        Mailbox {
+            queue: Mutex::new(VecDeque::new()),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Push `execution` into the mailbox, dropping the oldest queued one(s)
+    /// if it is already at capacity, and returning how many were dropped.
+    fn push(&self, execution: Execution) -> usize {
+        let mut queue = self.queue.lock().unwrap();
+        let mut dropped = 0;
+        while queue.len() >= SUBSCRIBER_QUEUE_CAPACITY {
+            queue.pop_front();
+            dropped += 1;
+        }
+        queue.push_back(execution);
+        self.available.notify_one();
+        dropped
+    }
+
+    /// Block until an execution is available and return it.
+    fn recv(&self) -> Execution {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(execution) = queue.pop_front() {
+                return execution;
+            }
+            queue = self.available.wait(queue).unwrap();
+        }
+    }
+}
+
+/// Fanout publishes every intercepted execution to zero or more attached
+/// subscribers, without ever blocking the primary path that persists
+/// events to the event file.
+///
+/// Publishing is a cheap, uncontended no-op when nobody is subscribed:
+/// `publish` skips straight past an empty subscriber list.
+#[derive(Clone, Default)]
+struct Fanout {
+    subscribers: Arc<Mutex<Vec<Arc<Mailbox>>>>,
+}
+
+impl Fanout {
+    fn subscribe(&self) -> Arc<Mailbox> {
+        let mailbox = Arc::new(Mailbox::new());
+        self.subscribers.lock().unwrap().push(mailbox.clone());
+        mailbox
+    }
+
+    fn unsubscribe(&self, mailbox: &Arc<Mailbox>) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|candidate| !Arc::ptr_eq(candidate, mailbox));
+    }
+
+    fn publish(&self, execution: &Execution) {
+        let subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        for mailbox in subscribers.iter() {
+            let dropped = mailbox.push(execution.clone());
+            if dropped > 0 {
+                log::warn!("subscriber lagged, dropped {} executions", dropped);
+            }
+        }
+    }
+}
+
+/// Collector receives envelopes from reporters over a TCP connection and
+/// persists them, one by one, into a sink shared by every connection.
+///
+/// Reporters publish to the address handed out by [`Collector::new`] through
+/// the `KEY_DESTINATION` environment variable. A second, read-only address
+/// lets external tools subscribe to a live stream of the same executions,
+/// without ever touching the on-disk event file.
+pub struct Collector {
+    listener: TcpListener,
+    codec: Codec,
+    subscriber_listener: TcpListener,
+    fanout: Fanout,
+}
+
+/// A reporter connection that completed the version handshake.
+///
+/// Keeping the negotiated `version` alongside the stream lets `serve` decide
+/// per connection whether the reporter actually supports newer wire-format
+/// features, such as MsgPack framing, rather than assuming every reporter
+/// speaking a compatible major version supports everything the collector
+/// does.
+struct Connection {
+    stream: TcpStream,
+    version: Version,
+}
+
+impl Collector {
+    /// Bind a collector to an ephemeral local address.
+    ///
+    /// Returns the collector together with the address reporters should
+    /// connect to, and the address external tools should connect to in
+    /// order to subscribe to the live execution stream.
+    pub fn new(codec: Codec) -> Result<(Self, SocketAddr, SocketAddr), anyhow::Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+
+        let subscriber_listener = TcpListener::bind("127.0.0.1:0")?;
+        let subscriber_address = subscriber_listener.local_addr()?;
+
+        let collector = Collector {
+            listener,
+            codec,
+            subscriber_listener,
+            fanout: Fanout::default(),
+        };
+        Ok((collector, address, subscriber_address))
+    }
+
+    /// Accept connections until the listener is closed, forwarding every
+    /// envelope received on them into `sink` and, as a side effect, to any
+    /// attached subscriber.
+    ///
+    /// Writes to `sink` are serialized across connections with a mutex, so
+    /// concurrently connected reporters never interleave their envelopes.
+    pub fn collect(self, sink: impl Write + Send + 'static) -> Result<(), anyhow::Error> {
+        let sink = Arc::new(Mutex::new(sink));
+        let codec = self.codec;
+        let fanout = self.fanout;
+
+        {
+            let fanout = fanout.clone();
+            let subscriber_listener = self.subscriber_listener;
+            thread::spawn(move || Self::serve_subscribers(subscriber_listener, fanout));
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let sink = sink.clone();
+            let fanout = fanout.clone();
+            thread::spawn(move || {
+                if let Err(error) = Self::accept(stream, sink, codec, fanout) {
+                    log::error!("collector connection closed with error: {:?}", error);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Perform the version handshake for a freshly accepted connection, then
+    /// serve envelopes on it until the reporter disconnects.
+    fn accept(
+        mut stream: TcpStream,
+        sink: Arc<Mutex<impl Write>>,
+        codec: Codec,
+        fanout: Fanout,
+    ) -> Result<(), anyhow::Error> {
+        let hello = Hello::read_from(&mut stream)?;
+        if !Version::CURRENT.is_compatible_with(&hello.version) {
+            log::warn!(
+                "rejecting reporter (crate v{}) speaking protocol v{}.{}, collector is v{}.{}",
+                hello.crate_version,
+                hello.version.major,
+                hello.version.minor,
+                Version::CURRENT.major,
+                Version::CURRENT.minor,
+            );
+            return Ok(());
+        }
+
+        let connection = Connection {
+            stream,
+            version: hello.version,
+        };
+        Self::serve(connection, sink, codec, fanout)
+    }
+
+    /// Read envelopes from `connection` until the reporter disconnects,
+    /// re-encoding each one with `codec` before handing it to `sink`.
+    ///
+    /// Every envelope is persisted as-is, `Started` and `Finished` alike; a
+    /// `Ledger` pairs them up along the way purely so we can log the
+    /// reconstructed outcome, not to gate what gets written. Executions from
+    /// `Started` events are also published on `fanout`, which is a cheap
+    /// no-op when nobody is subscribed.
+    fn serve(
+        mut connection: Connection,
+        sink: Arc<Mutex<impl Write>>,
+        codec: Codec,
+        fanout: Fanout,
+    ) -> Result<(), anyhow::Error> {
+        let codec = Self::negotiate_codec(codec, connection.version);
+        let mut ledger = Ledger::default();
+
+        loop {
+            let envelope = match Envelope::read_from(&mut connection.stream) {
+                Ok(envelope) => envelope,
+                Err(_) => break,
+            };
+
+            if let Some(outcome) = ledger.record(&envelope) {
+                log::debug!("process finished: {:?}", outcome);
+            }
+
+            if let Event::Started { execution, .. } = &envelope.event {
+                fanout.publish(execution);
+            }
+
+            let mut sink = sink.lock().unwrap();
+            envelope.write_into(&mut *sink, codec)?;
+        }
+
+        Ok(())
+    }
+
+    /// Downgrade `preferred` to `Codec::Json` if `reporter_version` predates
+    /// MsgPack support. The result only selects how this connection's
+    /// envelopes are encoded into the sink; nothing is sent back to the
+    /// reporter.
+    fn negotiate_codec(preferred: Codec, reporter_version: Version) -> Codec {
+        if preferred == Codec::MsgPack && reporter_version.minor < Version::MSGPACK_MINOR {
+            log::warn!(
+                "reporter speaks protocol v{}.{}, which predates MsgPack support; falling back to JSON",
+                reporter_version.major,
+                reporter_version.minor,
+            );
+            return Codec::Json;
+        }
+        preferred
+    }
+
+    /// Accept subscriber connections until the listener is closed, streaming
+    /// every published execution to each of them as newline-delimited JSON.
+    fn serve_subscribers(listener: TcpListener, fanout: Fanout) {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    log::error!("subscriber listener failed to accept: {:?}", error);
+                    continue;
+                }
+            };
+            let fanout = fanout.clone();
+            thread::spawn(move || Self::serve_subscriber(stream, fanout));
+        }
+    }
+
+    /// Forward every execution published on `fanout` to `stream` as a JSON
+    /// object followed by a newline, until the subscriber disconnects.
+    fn serve_subscriber(mut stream: TcpStream, fanout: Fanout) {
+        let mailbox = fanout.subscribe();
+
+        loop {
+            let execution = mailbox.recv();
+
+            let line = match serde_json::to_string(&execution) {
+                Ok(line) => line,
+                Err(error) => {
+                    log::error!("failed to serialize execution for subscriber: {:?}", error);
+                    continue;
+                }
+            };
+
+            if stream.write_all(line.as_bytes()).is_err() || stream.write_all(b"\n").is_err() {
+                break;
+            }
+        }
+
+        fanout.unsubscribe(&mailbox);
+    }
+}
@@ -16,7 +16,7 @@ pub mod reporter;
 /// It is used to identify the process that sends the execution report.
 /// Because the OS PID is not unique across a single build (PIDs are
 /// recycled), we need to use a new unique identifier to identify the process.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
 pub struct ReporterId(pub u64);
 
 impl ReporterId {
@@ -44,7 +44,7 @@ impl Default for ReporterId {
 }
 
 /// Process id is a OS identifier for a process.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
 pub struct ProcessId(pub u32);
 
 /// Execution is a representation of a process execution.
@@ -62,13 +62,36 @@ pub struct Execution {
 
 /// Represent a relevant life cycle event of a process.
 ///
-/// In the current implementation, we only have one event, the `Started` event.
-/// This event is sent when a process is started. It contains the process id
-/// and the execution information.
+/// A process is reported twice: once when it `Started`, carrying the
+/// execution information, and again when it `Finished`, carrying its
+/// outcome. The two share the same `pid` (and, once paired up in an
+/// `Envelope`, the same `rid`), so the collector can reassemble them into a
+/// single per-command outcome.
+///
+/// Untagged so `Started`'s JSON shape stays `{"pid":...,"execution":...}`,
+/// byte-identical to the struct this enum replaced. Event files written by
+/// that earlier version deserialize as `Started` without any migration.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub struct Event {
-    pub pid: ProcessId,
-    pub execution: Execution,
+#[serde(untagged)]
+pub enum Event {
+    Started {
+        pid: ProcessId,
+        execution: Execution,
+    },
+    Finished {
+        pid: ProcessId,
+        exit_code: i32,
+        duration_ms: u64,
+    },
+}
+
+impl Event {
+    pub fn pid(&self) -> &ProcessId {
+        match self {
+            Event::Started { pid, .. } => pid,
+            Event::Finished { pid, .. } => pid,
+        }
+    }
 }
 
 /// Envelope is a wrapper around the event.
@@ -81,6 +104,23 @@ pub struct Envelope {
     pub event: Event,
 }
 
+/// Selects the wire format used to encode the body of an `Envelope`.
+///
+/// JSON is the original, interoperable format. MsgPack is more compact and
+/// cheaper to decode, which matters once a collector has to process the
+/// tens of thousands of envelopes a large build produces.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Codec {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+/// Format tag written right after the length prefix, identifying the codec
+/// used to encode the envelope that follows.
+const FORMAT_TAG_JSON: u8 = 0x01;
+const FORMAT_TAG_MSGPACK: u8 = 0x02;
+
 impl Envelope {
     pub fn new(rid: &ReporterId, event: Event) -> Self {
         let timestamp = Utc::now().timestamp_millis() as u64;
@@ -93,8 +133,18 @@ impl Envelope {
 
     /// Read an envelope from a reader using TLV format.
     ///
-    /// The envelope is serialized using JSON and the length of the JSON
-    /// is written as a 4 byte big-endian integer before the JSON.
+    /// The 4 byte big-endian length prefix is followed by the envelope body.
+    /// The first byte of the body is a format tag (`0x01` for JSON, `0x02`
+    /// for MsgPack) that tells us how to decode the rest. Event files
+    /// written before the tag existed start their body with a JSON object,
+    /// i.e. a byte that is neither tag, so we fall back to treating the
+    /// whole body as untagged JSON for them. That alone isn't enough to read
+    /// those older files correctly: `Event` itself must also decode to the
+    /// same shape it had before it grew `Started`/`Finished` variants, which
+    /// is why `Event` is `#[serde(untagged)]`. Untagged decoding tells
+    /// `Started` and `Finished` apart only by which fields are present, so
+    /// it needs a self-describing encoding either way; see `write_into` for
+    /// why that holds for MsgPack too.
     pub fn read_from(reader: &mut impl Read) -> Result<Self, anyhow::Error> {
         let mut length_bytes = [0; 4];
         reader.read_exact(&mut length_bytes)?;
@@ -102,18 +152,115 @@ impl Envelope {
 
         let mut buffer = vec![0; length];
         reader.read_exact(&mut buffer)?;
-        let envelope = serde_json::from_slice(buffer.as_ref())?;
+
+        let envelope = match buffer.split_first() {
+            Some((&FORMAT_TAG_MSGPACK, body)) => rmp_serde::from_slice(body)?,
+            Some((&FORMAT_TAG_JSON, body)) => serde_json::from_slice(body)?,
+            _ => serde_json::from_slice(buffer.as_ref())?,
+        };
 
         Ok(envelope)
     }
 
     /// Write an envelope to a writer using TLV format.
     ///
-    /// The envelope is serialized using JSON and the length of the JSON
-    /// is written as a 4 byte big-endian integer before the JSON.
+    /// The body is prefixed with a format tag identifying `codec`, and the
+    /// length written as a 4 byte big-endian integer covers the tag plus
+    /// the encoded body.
+    ///
+    /// MsgPack structs encode as positional arrays by default, discarding
+    /// field names; `Event`'s untagged `Started`/`Finished` variants rely on
+    /// which fields are present to disambiguate, so we use `to_vec_named`
+    /// to encode structs as maps instead, matching JSON's self-describing
+    /// shape.
+    pub fn write_into(&self, writer: &mut impl Write, codec: Codec) -> Result<u32, anyhow::Error> {
+        let (tag, mut encoded) = match codec {
+            Codec::Json => (FORMAT_TAG_JSON, serde_json::to_vec(&self)?),
+            Codec::MsgPack => (FORMAT_TAG_MSGPACK, rmp_serde::to_vec_named(&self)?),
+        };
+
+        let mut body = Vec::with_capacity(1 + encoded.len());
+        body.push(tag);
+        body.append(&mut encoded);
+        let length = body.len() as u32;
+
+        writer.write_all(&length.to_be_bytes())?;
+        writer.write_all(&body)?;
+
+        Ok(length)
+    }
+}
+
+/// Version identifies the wire protocol spoken by a reporter or collector.
+///
+/// A difference in `major` means the two sides can misparse each other's
+/// messages, so connections are rejected on a `major` mismatch. A
+/// difference in `minor` is assumed additive (new optional fields, new
+/// message variants) and is tolerated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl Version {
+    /// The protocol version implemented by this build of the crate.
+    ///
+    /// `minor` must be bumped whenever a minor-gated feature (see
+    /// `MSGPACK_MINOR`) ships, so a current build's own handshake actually
+    /// advertises the support it ships.
+    pub const CURRENT: Version = Version { major: 1, minor: 1 };
+
+    /// The minor version at which MsgPack framing was introduced.
+    ///
+    /// For a reporter announcing an older minor, even one whose major
+    /// version matches, a collector persists that reporter's envelopes as
+    /// JSON instead of MsgPack.
+    pub const MSGPACK_MINOR: u16 = 1;
+
+    /// Whether a connection between `self` and `other` is safe to proceed.
+    pub fn is_compatible_with(&self, other: &Version) -> bool {
+        self.major == other.major
+    }
+}
+
+/// Hello is the first message a reporter sends when it connects to a
+/// collector, before any `Envelope`. It announces the protocol version so
+/// the collector can refuse a connection it cannot safely parse, and the
+/// crate version for diagnostics.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hello {
+    pub version: Version,
+    pub crate_version: String,
+}
+
+impl Hello {
+    /// Build the `Hello` announcing the protocol and crate version of this
+    /// build.
+    pub fn current() -> Self {
+        Hello {
+            version: Version::CURRENT,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Read a `Hello` from a reader using the same TLV framing as
+    /// `Envelope`, without a format tag: `Hello` is always JSON.
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, anyhow::Error> {
+        let mut length_bytes = [0; 4];
+        reader.read_exact(&mut length_bytes)?;
+        let length = u32::from_be_bytes(length_bytes) as usize;
+
+        let mut buffer = vec![0; length];
+        reader.read_exact(&mut buffer)?;
+        let hello = serde_json::from_slice(buffer.as_ref())?;
+
+        Ok(hello)
+    }
+
+    /// Write a `Hello` to a writer using the same TLV framing as `Envelope`.
     pub fn write_into(&self, writer: &mut impl Write) -> Result<u32, anyhow::Error> {
-        let serialized_envelope = serde_json::to_string(&self)?;
-        let bytes = serialized_envelope.into_bytes();
+        let bytes = serde_json::to_vec(&self)?;
         let length = bytes.len() as u32;
 
         writer.write_all(&length.to_be_bytes())?;
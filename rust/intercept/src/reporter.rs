@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::env;
+use std::net::TcpStream;
+use std::sync::Mutex;
+
+use crate::{Codec, Envelope, Event, Execution, Hello, ProcessId, ReporterId, KEY_DESTINATION};
+
+/// Reporter sends process lifecycle events to the collector listening at
+/// the address published through the `KEY_DESTINATION` environment
+/// variable.
+pub struct Reporter {
+    id: ReporterId,
+    codec: Codec,
+    connection: Mutex<TcpStream>,
+}
+
+impl Reporter {
+    /// Connect to the collector address found in `KEY_DESTINATION`, send the
+    /// version handshake, then use `codec` to encode every envelope sent on
+    /// the connection.
+    pub fn new(codec: Codec) -> Result<Self, anyhow::Error> {
+        let address = env::var(KEY_DESTINATION)?;
+        let mut connection = TcpStream::connect(address)?;
+        Hello::current().write_into(&mut connection)?;
+
+        Ok(Reporter {
+            id: ReporterId::new(),
+            codec,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Report that a process has started.
+    pub fn report_started(&self, pid: ProcessId, execution: Execution) -> Result<(), anyhow::Error> {
+        self.send(Event::Started { pid, execution })
+    }
+
+    /// Report that a process has finished.
+    pub fn report_finished(
+        &self,
+        pid: ProcessId,
+        exit_code: i32,
+        duration_ms: u64,
+    ) -> Result<(), anyhow::Error> {
+        self.send(Event::Finished {
+            pid,
+            exit_code,
+            duration_ms,
+        })
+    }
+
+    fn send(&self, event: Event) -> Result<(), anyhow::Error> {
+        let envelope = Envelope::new(&self.id, event);
+
+        let mut connection = self.connection.lock().unwrap();
+        envelope.write_into(&mut *connection, self.codec)?;
+
+        Ok(())
+    }
+}